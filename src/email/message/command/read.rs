@@ -1,7 +1,12 @@
-use std::{fmt, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use clap::Parser;
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
 use email::{backend::feature::BackendFeatureSource, config::Config};
 use pimalaya_tui::{
     himalaya::backend::BackendBuilder,
@@ -23,21 +28,68 @@ pub struct StructuredMessage {
     pub id: String,
     /// The message headers (From, To, Subject, Date, etc.).
     pub headers: MessageHeaders,
-    /// The plain text body of the message.
+    /// The decoded body of the selected part (see `--part`).
     pub body: String,
+    /// The full MIME part tree, in document order.
+    pub parts: Vec<BodyPart>,
+    /// Every non-inline part discovered in the message, i.e. the ones a
+    /// mail client would offer to download separately.
+    pub attachments: Vec<Attachment>,
+}
+
+/// Describes a single attachment found in a message.
+#[derive(Clone, Debug, Serialize)]
+pub struct Attachment {
+    /// Index into the message's MIME part table (see `BodyPart::index`).
+    pub part: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+    pub content_type: String,
+    pub size: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<String>,
+    /// Path the attachment was written to, when `--save-attachments` is used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saved_path: Option<String>,
+}
+
+/// Describes a single part found while walking a message's MIME tree.
+///
+/// Multipart nodes (`multipart/alternative`, `multipart/mixed`, etc.)
+/// only carry `multipart_subtype`; leaf parts carry everything else.
+#[derive(Clone, Debug, Serialize)]
+pub struct BodyPart {
+    /// Position of this part in `mail_parser`'s part table.
+    ///
+    /// This is the value to pass to `--part` to select it explicitly.
+    pub index: usize,
+    /// The part's `Content-Type`, e.g. `text/plain` or `multipart/mixed`.
+    pub content_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disposition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    /// Set to the multipart subtype (`alternative`, `mixed`, `related`,
+    /// …) for multipart nodes, `None` for leaf parts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multipart_subtype: Option<String>,
 }
 
 /// Represents the headers of a message.
 #[derive(Clone, Debug, Default, Serialize)]
 pub struct MessageHeaders {
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub from: Option<String>,
+    pub from: Option<AddressList>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub to: Option<String>,
+    pub to: Option<AddressList>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub cc: Option<String>,
+    pub cc: Option<AddressList>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub bcc: Option<String>,
+    pub bcc: Option<AddressList>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub subject: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -46,6 +98,96 @@ pub struct MessageHeaders {
     pub message_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub in_reply_to: Option<String>,
+    /// The `References` header, oldest ancestor first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+    /// A stable key for the conversation this message belongs to,
+    /// derived from the oldest entry of `references`, falling back to
+    /// `in_reply_to` and then `message_id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thread_id: Option<String>,
+    /// The first ~256 characters of the decoded plain text body, with
+    /// whitespace collapsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<String>,
+}
+
+/// A list of addresses from a single header (`From`, `To`, etc).
+///
+/// Serializes as the plain array of [`EmailAddress`] it wraps, but
+/// implements [`fmt::Display`] as the human-readable joined form used
+/// for terminal output.
+#[derive(Clone, Debug, Default)]
+pub struct AddressList(pub Vec<EmailAddress>);
+
+impl fmt::Display for AddressList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `mail_parser::Address` is either all plain addresses or all
+        // groups, never a mix: plain addresses join with ", ", groups
+        // join with a bare space. A group is recognised by having
+        // members, so a group with no members prints as its bare name
+        // and joins with ", " like a plain address.
+        let glue = if self.0.iter().any(|a| !a.members.is_empty()) {
+            " "
+        } else {
+            ", "
+        };
+
+        let addrs = self
+            .0
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<_>>()
+            .join(glue);
+        write!(f, "{addrs}")
+    }
+}
+
+impl Serialize for AddressList {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+/// A single email address, modeled on JMAP's EmailAddress object.
+///
+/// A group address (RFC 2822 `group: a@b, c@d;`) is represented as a
+/// `name` with no `email` and its members nested under `members`.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EmailAddress {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub members: Vec<EmailAddress>,
+}
+
+impl fmt::Display for EmailAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.members.is_empty() {
+            // Matches the pre-existing `format_address`, which only
+            // ever printed the member's address, never its name.
+            let name = self.name.as_deref().unwrap_or("");
+            let members = self
+                .members
+                .iter()
+                .filter_map(|m| m.email.as_deref())
+                .collect::<Vec<_>>()
+                .join(", ");
+            return write!(f, "{name}: {members};");
+        }
+
+        match (&self.name, &self.email) {
+            (Some(name), Some(email)) => write!(f, "{name} <{email}>"),
+            (None, Some(email)) => write!(f, "{email}"),
+            (Some(name), None) => write!(f, "{name}"),
+            (None, None) => Ok(()),
+        }
+    }
 }
 
 /// A collection of structured messages.
@@ -100,6 +242,24 @@ pub struct MessageReadCommand {
     #[arg(conflicts_with = "no_headers")]
     pub headers: Vec<String>,
 
+    /// Select which MIME part to use as the body.
+    ///
+    /// Accepts either the numeric index of a part, as shown in the
+    /// `parts` list of the JSON output, or a MIME type such as
+    /// `text/html`. When the MIME type matches several parts, the
+    /// first one found is used. Defaults to the best `text/plain`
+    /// part, falling back to `text/html`.
+    #[arg(long, value_name = "INDEX-OR-MIME-TYPE")]
+    pub part: Option<String>,
+
+    /// Write every non-inline attachment to DIR.
+    ///
+    /// Filenames are sanitized and de-duplicated on collision. The
+    /// path each attachment was written to is recorded in the JSON
+    /// output alongside its metadata.
+    #[arg(long, value_name = "DIR")]
+    pub save_attachments: Option<PathBuf>,
+
     #[command(flatten)]
     pub account: AccountNameFlag,
 }
@@ -139,7 +299,12 @@ impl MessageReadCommand {
             backend.get_messages(folder, ids).await
         }?;
 
+        if let Some(dir) = &self.save_attachments {
+            fs::create_dir_all(dir)?;
+        }
+
         let mut structured_messages = Vec::new();
+        let mut seen_filenames = HashMap::new();
 
         for (idx, email) in emails.to_vec().iter().enumerate() {
             let tpl = email
@@ -154,27 +319,52 @@ impl MessageReadCommand {
                 })
                 .await?;
 
-            // Extract headers from the parsed email
+            // Extract headers and body structure from the parsed MIME tree,
+            // falling back to the flattened template if parsing failed.
             let parsed = email.parsed();
-            let headers = if let Ok(parsed) = parsed {
-                MessageHeaders {
-                    from: parsed.from().map(format_address),
-                    to: parsed.to().map(format_address),
-                    cc: parsed.cc().map(format_address),
-                    bcc: parsed.bcc().map(format_address),
+            let (headers, parts, body, attachments) = if let Ok(parsed) = parsed {
+                let message_id = parsed.message_id().map(|s| s.to_string());
+                let in_reply_to = parsed
+                    .in_reply_to()
+                    .as_text_list()
+                    .and_then(|ids| ids.first().map(|s| s.to_string()));
+                let references = parsed
+                    .references()
+                    .as_text_list()
+                    .map(|ids| ids.iter().map(|s| s.to_string()).collect())
+                    .unwrap_or_default();
+                let thread_id = compute_thread_id(&references, in_reply_to.as_deref(), message_id.as_deref());
+
+                let headers = MessageHeaders {
+                    from: parsed.from().map(parse_address),
+                    to: parsed.to().map(parse_address),
+                    cc: parsed.cc().map(parse_address),
+                    bcc: parsed.bcc().map(parse_address),
                     subject: parsed.subject().map(|s| s.to_string()),
                     date: parsed.date().map(|d| d.to_rfc3339()),
-                    message_id: parsed.message_id().map(|s| s.to_string()),
-                    in_reply_to: parsed.in_reply_to().as_text_list()
-                        .and_then(|ids| ids.first().map(|s| s.to_string())),
-                }
+                    message_id,
+                    in_reply_to,
+                    references,
+                    thread_id,
+                    preview: compute_preview(&parsed),
+                };
+                let parts = enumerate_parts(&parsed);
+                let body = select_body(&parsed, &parts, self.part.as_deref())?;
+                let attachments = build_attachments(
+                    &parsed,
+                    self.save_attachments.as_deref(),
+                    &mut seen_filenames,
+                )?;
+                (headers, parts, body, attachments)
             } else {
-                MessageHeaders::default()
+                (
+                    MessageHeaders::default(),
+                    Vec::new(),
+                    extract_body_from_template(&tpl),
+                    Vec::new(),
+                )
             };
 
-            // Extract body from template (the body part after headers)
-            let body = extract_body_from_template(&tpl);
-
             // Use the envelope ID if available, otherwise use index
             let id = ids.get(idx).map(|s| s.to_string()).unwrap_or_else(|| idx.to_string());
 
@@ -182,6 +372,8 @@ impl MessageReadCommand {
                 id,
                 headers,
                 body,
+                parts,
+                attachments,
             });
         }
 
@@ -189,43 +381,72 @@ impl MessageReadCommand {
     }
 }
 
-/// Formats an Address to a human-readable string.
-fn format_address(addr: &mail_parser::Address) -> String {
+/// Parses a `mail_parser::Address` into a structured [`AddressList`].
+fn parse_address(addr: &mail_parser::Address) -> AddressList {
     match addr {
         mail_parser::Address::List(addrs) => {
-            addrs
-                .iter()
-                .filter_map(|a| {
-                    match (&a.name, &a.address) {
-                        (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
-                        (None, Some(email)) => Some(email.to_string()),
-                        (Some(name), None) => Some(name.to_string()),
-                        (None, None) => None,
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join(", ")
+            AddressList(addrs.iter().filter_map(email_address_from).collect())
         }
-        mail_parser::Address::Group(groups) => {
+        mail_parser::Address::Group(groups) => AddressList(
             groups
                 .iter()
-                .map(|g| {
-                    let name = g.name.as_deref().unwrap_or("");
-                    let members = g
-                        .addresses
-                        .iter()
-                        .filter_map(|a| a.address.as_ref().map(|s| s.to_string()))
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    format!("{}: {};", name, members)
+                .map(|g| EmailAddress {
+                    name: g.name.as_ref().map(|s| s.to_string()),
+                    email: None,
+                    members: g.addresses.iter().filter_map(email_address_from).collect(),
                 })
-                .collect::<Vec<_>>()
-                .join(" ")
-        }
+                .collect(),
+        ),
     }
 }
 
+/// Converts a single `mail_parser` address into an [`EmailAddress`],
+/// dropping entries with neither a name nor an address.
+fn email_address_from(addr: &mail_parser::Addr) -> Option<EmailAddress> {
+    if addr.name.is_none() && addr.address.is_none() {
+        return None;
+    }
+
+    Some(EmailAddress {
+        name: addr.name.as_ref().map(|s| s.to_string()),
+        email: addr.address.as_ref().map(|s| s.to_string()),
+        members: Vec::new(),
+    })
+}
+
+/// Derives a stable conversation key for a message: the oldest entry
+/// of `References`, falling back to `In-Reply-To` and then the
+/// message's own `Message-ID` so every message gets a thread key.
+fn compute_thread_id(
+    references: &[String],
+    in_reply_to: Option<&str>,
+    message_id: Option<&str>,
+) -> Option<String> {
+    references
+        .first()
+        .cloned()
+        .or_else(|| in_reply_to.map(|s| s.to_string()))
+        .or_else(|| message_id.map(|s| s.to_string()))
+}
+
+/// Builds a short preview snippet from the message's decoded plain
+/// text body: whitespace collapsed, truncated to ~256 characters.
+fn compute_preview(parsed: &mail_parser::Message) -> Option<String> {
+    let text = parsed.body_text(0)?;
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if collapsed.is_empty() {
+        return None;
+    }
+
+    Some(collapsed.chars().take(256).collect())
+}
+
 /// Extracts the body from a template string (after the header section).
+///
+/// Only used as a fallback when the message could not be parsed as
+/// MIME, in which case [`enumerate_parts`] and [`select_body`] are not
+/// available.
 fn extract_body_from_template(tpl: &str) -> String {
     // The template format has headers followed by an empty line, then the body
     if let Some(pos) = tpl.find("\n\n") {
@@ -237,3 +458,468 @@ fn extract_body_from_template(tpl: &str) -> String {
         tpl.to_string()
     }
 }
+
+/// Walks a message's MIME tree and returns its parts in document order.
+fn enumerate_parts(parsed: &mail_parser::Message) -> Vec<BodyPart> {
+    let mut out = Vec::new();
+    if !parsed.parts.is_empty() {
+        walk_part(parsed, 0, &mut out);
+    }
+    out
+}
+
+/// Recursively appends `part_idx` (and, if it is a multipart node, its
+/// children) to `out`.
+fn walk_part(parsed: &mail_parser::Message, part_idx: usize, out: &mut Vec<BodyPart>) {
+    let Some(part) = parsed.parts.get(part_idx) else {
+        return;
+    };
+
+    if let mail_parser::PartType::Multipart(children) = &part.body {
+        let subtype = part
+            .content_type()
+            .and_then(|ct| ct.subtype())
+            .unwrap_or("mixed")
+            .to_string();
+
+        out.push(BodyPart {
+            index: part_idx,
+            content_type: content_type_of(part),
+            charset: None,
+            content_id: None,
+            disposition: None,
+            size: None,
+            multipart_subtype: Some(subtype),
+        });
+
+        for &child in children {
+            walk_part(parsed, child, out);
+        }
+    } else {
+        out.push(BodyPart {
+            index: part_idx,
+            content_type: content_type_of(part),
+            charset: part
+                .content_type()
+                .and_then(|ct| ct.attribute("charset"))
+                .map(|s| s.to_string()),
+            content_id: part.content_id().map(|s| s.to_string()),
+            disposition: part.content_disposition().map(|cd| cd.ctype().to_string()),
+            size: Some(part.len()),
+            multipart_subtype: None,
+        });
+    }
+}
+
+/// Formats a part's `Content-Type` as `type/subtype`, inferring a
+/// reasonable default from its body when the header is missing.
+fn content_type_of(part: &mail_parser::MessagePart) -> String {
+    part.content_type()
+        .map(|ct| match ct.subtype() {
+            Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+            None => ct.ctype().to_string(),
+        })
+        .unwrap_or_else(|| match &part.body {
+            mail_parser::PartType::Html(_) => "text/html".to_string(),
+            mail_parser::PartType::Binary(_) | mail_parser::PartType::InlineBinary(_) => {
+                "application/octet-stream".to_string()
+            }
+            _ => "text/plain".to_string(),
+        })
+}
+
+/// Decodes a leaf part's text content, or an empty string for binary parts.
+fn decode_part_text(part: &mail_parser::MessagePart) -> String {
+    match &part.body {
+        mail_parser::PartType::Text(text) | mail_parser::PartType::Html(text) => text.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Picks the body text to show, honouring an explicit `--part` request
+/// (by index or MIME type) and otherwise defaulting to the best
+/// `text/plain` part, falling back to `text/html`.
+///
+/// An explicit `requested` part that does not resolve to anything is
+/// an error: silently falling back would make a mistyped `--part`
+/// look like it was honoured.
+fn select_body(parsed: &mail_parser::Message, parts: &[BodyPart], requested: Option<&str>) -> Result<String> {
+    if let Some(requested) = requested {
+        if let Ok(index) = requested.parse::<usize>() {
+            return parsed
+                .parts
+                .get(index)
+                .map(decode_part_text)
+                .ok_or_else(|| eyre!("no part found at index {index}"));
+        }
+
+        return parts
+            .iter()
+            .find(|p| p.content_type.eq_ignore_ascii_case(requested))
+            .and_then(|p| parsed.parts.get(p.index))
+            .map(decode_part_text)
+            .ok_or_else(|| eyre!("no part found with MIME type `{requested}`"));
+    }
+
+    Ok(parsed
+        .body_text(0)
+        .or_else(|| parsed.body_html(0))
+        .map(|cow| cow.into_owned())
+        .unwrap_or_default())
+}
+
+/// Collects the non-inline parts of a message, optionally writing each
+/// one to `save_dir`. `seen_filenames` tracks names already used under
+/// `save_dir` so repeated filenames across parts or messages do not
+/// collide.
+fn build_attachments(
+    parsed: &mail_parser::Message,
+    save_dir: Option<&Path>,
+    seen_filenames: &mut HashMap<String, usize>,
+) -> Result<Vec<Attachment>> {
+    let mut out = Vec::new();
+
+    for &part_idx in &parsed.attachments {
+        let Some(part) = parsed.parts.get(part_idx) else {
+            continue;
+        };
+
+        let filename = part.attachment_name().map(|s| s.to_string());
+        let saved_path = match save_dir {
+            Some(dir) => Some(write_attachment(dir, filename.as_deref(), part_idx, part.contents(), seen_filenames)?),
+            None => None,
+        };
+
+        out.push(Attachment {
+            part: part_idx,
+            filename,
+            content_type: content_type_of(part),
+            size: part.len(),
+            content_id: part.content_id().map(|s| s.to_string()),
+            saved_path: saved_path.map(|p| p.display().to_string()),
+        });
+    }
+
+    Ok(out)
+}
+
+/// Writes a single attachment's contents under `dir`, sanitizing its
+/// filename and de-duplicating it against `seen_filenames`.
+fn write_attachment(
+    dir: &Path,
+    filename: Option<&str>,
+    part_idx: usize,
+    contents: &[u8],
+    seen_filenames: &mut HashMap<String, usize>,
+) -> Result<PathBuf> {
+    let sanitized = sanitize_attachment_filename(filename, part_idx);
+    let path = dedupe_attachment_path(dir, &sanitized, seen_filenames);
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// Strips any directory components, surrounding whitespace and
+/// control characters, and leading dots from a proposed attachment
+/// filename, replacing control characters inside the name with `_`.
+/// Falls back to a name derived from the part index when the result
+/// would be empty.
+fn sanitize_attachment_filename(filename: Option<&str>, part_idx: usize) -> String {
+    let fallback = || format!("attachment-{part_idx}");
+
+    let name = filename
+        .map(|name| name.rsplit(['/', '\\']).next().unwrap_or(name))
+        .unwrap_or("")
+        .trim_matches(|c: char| c.is_whitespace() || c.is_control());
+
+    let sanitized: String = name
+        .chars()
+        .map(|c| if c.is_control() { '_' } else { c })
+        .collect();
+
+    match sanitized.trim_start_matches('.') {
+        "" => fallback(),
+        name => name.to_string(),
+    }
+}
+
+/// Returns a path under `dir` for `filename`, appending `-N` before the
+/// extension (if any) when the name was already used.
+fn dedupe_attachment_path(dir: &Path, filename: &str, seen_filenames: &mut HashMap<String, usize>) -> PathBuf {
+    let count = seen_filenames.entry(filename.to_string()).or_insert(0);
+    let path = if *count == 0 {
+        dir.join(filename)
+    } else {
+        match filename.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => dir.join(format!("{stem}-{count}.{ext}")),
+            _ => dir.join(format!("{filename}-{count}")),
+        }
+    };
+    *count += 1;
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &str) -> mail_parser::Message<'_> {
+        mail_parser::MessageParser::default()
+            .parse(raw.as_bytes())
+            .expect("raw message should parse")
+    }
+
+    const MULTIPART_ALTERNATIVE: &str = concat!(
+        "From: a@b.com\r\n",
+        "To: c@d.com\r\n",
+        "Subject: hi\r\n",
+        "Content-Type: multipart/alternative; boundary=\"b\"\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "plain body\r\n",
+        "--b\r\n",
+        "Content-Type: text/html\r\n",
+        "\r\n",
+        "<p>html body</p>\r\n",
+        "--b--\r\n",
+    );
+
+    #[test]
+    fn select_body_defaults_to_plain_text() {
+        let parsed = parse(MULTIPART_ALTERNATIVE);
+        let parts = enumerate_parts(&parsed);
+        let body = select_body(&parsed, &parts, None).unwrap();
+        assert_eq!(body.trim(), "plain body");
+    }
+
+    #[test]
+    fn select_body_honours_explicit_mime_type() {
+        let parsed = parse(MULTIPART_ALTERNATIVE);
+        let parts = enumerate_parts(&parsed);
+        let body = select_body(&parsed, &parts, Some("text/html")).unwrap();
+        assert_eq!(body.trim(), "<p>html body</p>");
+    }
+
+    #[test]
+    fn select_body_honours_explicit_index() {
+        let parsed = parse(MULTIPART_ALTERNATIVE);
+        let parts = enumerate_parts(&parsed);
+        let plain_index = parts
+            .iter()
+            .find(|p| p.content_type == "text/plain")
+            .unwrap()
+            .index;
+        let body = select_body(&parsed, &parts, Some(&plain_index.to_string())).unwrap();
+        assert_eq!(body.trim(), "plain body");
+    }
+
+    #[test]
+    fn select_body_errors_on_out_of_range_index() {
+        let parsed = parse(MULTIPART_ALTERNATIVE);
+        let parts = enumerate_parts(&parsed);
+        assert!(select_body(&parsed, &parts, Some("99")).is_err());
+    }
+
+    #[test]
+    fn select_body_errors_on_unmatched_mime_type() {
+        let parsed = parse(MULTIPART_ALTERNATIVE);
+        let parts = enumerate_parts(&parsed);
+        assert!(select_body(&parsed, &parts, Some("message/rfc822")).is_err());
+    }
+
+    fn addr(name: Option<&str>, address: Option<&str>) -> mail_parser::Addr<'static> {
+        mail_parser::Addr {
+            name: name.map(|s| s.to_string().into()),
+            address: address.map(|s| s.to_string().into()),
+        }
+    }
+
+    #[test]
+    fn parse_address_drops_empty_entries() {
+        let list = mail_parser::Address::List(vec![
+            addr(Some("Bob"), Some("bob@x.com")),
+            addr(None, None),
+        ]);
+        let parsed = parse_address(&list);
+        assert_eq!(parsed.0.len(), 1);
+        assert_eq!(parsed.0[0].name.as_deref(), Some("Bob"));
+        assert_eq!(parsed.0[0].email.as_deref(), Some("bob@x.com"));
+    }
+
+    #[test]
+    fn parse_address_list_display_matches_legacy_format() {
+        let list = mail_parser::Address::List(vec![
+            addr(Some("Bob"), Some("bob@x.com")),
+            addr(None, Some("carol@x.com")),
+        ]);
+        let parsed = parse_address(&list);
+        assert_eq!(parsed.to_string(), "Bob <bob@x.com>, carol@x.com");
+    }
+
+    #[test]
+    fn parse_address_group_display_matches_legacy_format() {
+        let groups = mail_parser::Address::Group(vec![
+            mail_parser::Group {
+                name: Some("team".to_string().into()),
+                addresses: vec![addr(Some("Bob"), Some("bob@x.com")), addr(None, Some("carol@x.com"))],
+            },
+            mail_parser::Group {
+                name: Some("others".to_string().into()),
+                addresses: vec![addr(None, Some("dan@x.com"))],
+            },
+        ]);
+        let parsed = parse_address(&groups);
+        assert_eq!(parsed.to_string(), "team: bob@x.com, carol@x.com; others: dan@x.com;");
+    }
+
+    #[test]
+    fn sanitize_attachment_filename_strips_path_components() {
+        assert_eq!(
+            sanitize_attachment_filename(Some("../../etc/passwd"), 0),
+            "passwd"
+        );
+        assert_eq!(
+            sanitize_attachment_filename(Some("C:\\Users\\bob\\report.pdf"), 0),
+            "report.pdf"
+        );
+    }
+
+    #[test]
+    fn sanitize_attachment_filename_strips_control_chars_and_leading_dots() {
+        assert_eq!(sanitize_attachment_filename(Some("\0\0"), 3), "attachment-3");
+        assert_eq!(sanitize_attachment_filename(Some("..hidden"), 0), "hidden");
+        assert_eq!(sanitize_attachment_filename(Some("a\nb.txt"), 0), "a_b.txt");
+    }
+
+    #[test]
+    fn sanitize_attachment_filename_falls_back_when_missing() {
+        assert_eq!(sanitize_attachment_filename(None, 7), "attachment-7");
+    }
+
+    #[test]
+    fn dedupe_attachment_path_suffixes_collisions_before_extension() {
+        let dir = Path::new("/tmp/attachments");
+        let mut seen = HashMap::new();
+
+        let first = dedupe_attachment_path(dir, "report.pdf", &mut seen);
+        let second = dedupe_attachment_path(dir, "report.pdf", &mut seen);
+        let third = dedupe_attachment_path(dir, "report.pdf", &mut seen);
+
+        assert_eq!(first, dir.join("report.pdf"));
+        assert_eq!(second, dir.join("report-1.pdf"));
+        assert_eq!(third, dir.join("report-2.pdf"));
+    }
+
+    #[test]
+    fn dedupe_attachment_path_without_extension() {
+        let dir = Path::new("/tmp/attachments");
+        let mut seen = HashMap::new();
+
+        let first = dedupe_attachment_path(dir, "README", &mut seen);
+        let second = dedupe_attachment_path(dir, "README", &mut seen);
+
+        assert_eq!(first, dir.join("README"));
+        assert_eq!(second, dir.join("README-1"));
+    }
+
+    #[test]
+    fn compute_thread_id_prefers_oldest_reference() {
+        let references = vec!["<root@a>".to_string(), "<mid@a>".to_string()];
+        let thread_id = compute_thread_id(&references, Some("<mid@a>"), Some("<leaf@a>"));
+        assert_eq!(thread_id.as_deref(), Some("<root@a>"));
+    }
+
+    #[test]
+    fn compute_thread_id_falls_back_to_in_reply_to_then_message_id() {
+        assert_eq!(
+            compute_thread_id(&[], Some("<parent@a>"), Some("<leaf@a>")).as_deref(),
+            Some("<parent@a>")
+        );
+        assert_eq!(
+            compute_thread_id(&[], None, Some("<leaf@a>")).as_deref(),
+            Some("<leaf@a>")
+        );
+        assert_eq!(compute_thread_id(&[], None, None), None);
+    }
+
+    #[test]
+    fn compute_preview_collapses_whitespace_and_truncates() {
+        let raw = format!(
+            "From: a@b.com\r\nSubject: hi\r\n\r\n{}\r\n",
+            "word ".repeat(100)
+        );
+        let parsed = parse(&raw);
+        let preview = compute_preview(&parsed).unwrap();
+        assert!(preview.chars().count() <= 256);
+        assert!(!preview.contains("  "));
+    }
+
+    #[test]
+    fn compute_preview_is_none_for_empty_body() {
+        let parsed = parse("From: a@b.com\r\nSubject: hi\r\n\r\n");
+        assert_eq!(compute_preview(&parsed), None);
+    }
+
+    const MULTIPART_MIXED_WITH_ATTACHMENT: &str = concat!(
+        "From: a@b.com\r\n",
+        "To: c@d.com\r\n",
+        "Subject: hi\r\n",
+        "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "see attached\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain\r\n",
+        "Content-Disposition: attachment; filename=\"notes.txt\"\r\n",
+        "Content-ID: <notes@x>\r\n",
+        "\r\n",
+        "hello from the attachment\r\n",
+        "--b--\r\n",
+    );
+
+    /// Returns a fresh, unique scratch directory under the OS temp dir,
+    /// creating it if needed. Tests that use it are responsible for
+    /// whatever cleanup they need.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("himalaya-test-{name}-{:p}", name));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn build_attachments_discovers_metadata_without_saving() {
+        let parsed = parse(MULTIPART_MIXED_WITH_ATTACHMENT);
+        let mut seen = HashMap::new();
+        let attachments = build_attachments(&parsed, None, &mut seen).unwrap();
+
+        assert_eq!(attachments.len(), 1);
+        let attachment = &attachments[0];
+        assert_eq!(attachment.filename.as_deref(), Some("notes.txt"));
+        assert_eq!(attachment.content_type, "text/plain");
+        assert_eq!(attachment.size, "hello from the attachment".len());
+        assert_eq!(attachment.content_id.as_deref(), Some("notes@x"));
+        assert_eq!(attachment.saved_path, None);
+    }
+
+    #[test]
+    fn build_attachments_writes_file_matching_decoded_content() {
+        let parsed = parse(MULTIPART_MIXED_WITH_ATTACHMENT);
+        let dir = scratch_dir("build-attachments-write");
+        let mut seen = HashMap::new();
+
+        let attachments = build_attachments(&parsed, Some(&dir), &mut seen).unwrap();
+
+        assert_eq!(attachments.len(), 1);
+        let attachment = &attachments[0];
+        let saved_path = attachment.saved_path.as_deref().unwrap();
+        assert_eq!(saved_path, dir.join("notes.txt").display().to_string());
+
+        let on_disk = fs::read_to_string(saved_path).unwrap();
+        assert_eq!(on_disk, "hello from the attachment");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use clap::Parser;
+use color_eyre::{eyre::eyre, Result};
+use email::{backend::feature::BackendFeatureSource, config::Config};
+use pimalaya_tui::{
+    himalaya::backend::BackendBuilder,
+    terminal::{cli::printer::Printer, config::TomlConfig as _},
+};
+use tracing::{info, warn};
+
+use crate::{account::arg::name::AccountNameFlag, config::TomlConfig};
+
+use super::super::compose_hooks::{run_compose_hooks, ComposeHooksConfig, Severity};
+
+/// Send a raw draft message.
+///
+/// Before the draft is handed to the backend, the configured compose
+/// hooks run against it: a hook that only warns is printed and
+/// sending continues, a hook that errors aborts the send.
+///
+/// NOTE: reading hook configuration from `[composing]` in the TOML
+/// config file is not wired up yet — `TomlConfig` lives outside this
+/// chunk of the tree, so it cannot be extended with a `composing`
+/// field from here. That TOML wiring is tracked as a follow-up request
+/// against `TomlConfig`. Until then, `--disable-compose-hook` is the
+/// supported way to toggle a built-in hook off.
+#[derive(Debug, Parser)]
+pub struct MessageSendCommand {
+    /// Path to the raw RFC 5322 draft to send, or `-` for stdin.
+    #[arg(value_name = "PATH")]
+    pub path: String,
+
+    /// Name of a built-in compose hook to skip, e.g.
+    /// `past-date-warn`. May be given multiple times.
+    ///
+    /// Stands in for `[composing.disabled_compose_hooks]` in the TOML
+    /// config until that section is wired up (see the note on
+    /// `MessageSendCommand`).
+    #[arg(long = "disable-compose-hook", value_name = "NAME")]
+    pub disabled_compose_hooks: Vec<String>,
+
+    #[command(flatten)]
+    pub account: AccountNameFlag,
+}
+
+impl MessageSendCommand {
+    pub async fn execute(self, printer: &mut impl Printer, config: &TomlConfig) -> Result<()> {
+        info!("executing send message command");
+
+        let (toml_account_config, account_config) = config
+            .clone()
+            .into_account_configs(self.account.name.as_deref(), |c: &Config, name| {
+                c.account(name).ok()
+            })?;
+
+        let account_config = Arc::new(account_config);
+
+        let backend = BackendBuilder::new(
+            Arc::new(toml_account_config),
+            account_config.clone(),
+            |builder| {
+                builder
+                    .without_features()
+                    .with_send_message(BackendFeatureSource::Context)
+            },
+        )
+        .build()
+        .await?;
+
+        let raw = if self.path == "-" {
+            std::io::read_to_string(std::io::stdin())?
+        } else {
+            std::fs::read_to_string(&self.path)?
+        };
+
+        let draft = mail_parser::MessageParser::default()
+            .parse(raw.as_bytes())
+            .ok_or_else(|| eyre!("failed to parse draft as a MIME message"))?;
+
+        // `[composing]` TOML wiring isn't available yet (see the NOTE on
+        // `MessageSendCommand`), so only the CLI-level disable flag is
+        // honoured here; other `ComposeHooksConfig` fields keep their
+        // defaults until that follow-up lands.
+        let hooks_config = ComposeHooksConfig {
+            disabled_compose_hooks: self.disabled_compose_hooks,
+            ..ComposeHooksConfig::default()
+        };
+
+        for (severity, message) in run_compose_hooks(&draft, &hooks_config)? {
+            debug_assert_eq!(severity, Severity::Warning, "errors abort before reaching here");
+            warn!("{message}");
+            printer.out(format!("warning: {message}\n"))?;
+        }
+
+        backend.send_message(raw.as_bytes()).await?;
+
+        printer.out("message sent\n".to_string())
+    }
+}
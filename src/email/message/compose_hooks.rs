@@ -0,0 +1,414 @@
+//! Pre-submission compose hooks.
+//!
+//! A [`ComposeHook`] inspects a parsed draft before it is sent and
+//! reports problems as `(Severity, message)` findings,
+//! using the same `mail_parser` parsing [`MessageReadCommand`] uses
+//! for reads. [`run_compose_hooks`] is called from
+//! [`MessageSendCommand`], right before the draft is handed to the
+//! backend, the counterpart of `to_read_tpl` on the read side. Saving
+//! a draft does not run the hooks.
+//!
+//! [`MessageReadCommand`]: super::command::read::MessageReadCommand
+//! [`MessageSendCommand`]: super::command::send::MessageSendCommand
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use color_eyre::{eyre::eyre, Result};
+use serde::{Deserialize, Serialize};
+
+/// How serious a compose hook finding is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// Printed to the user, submission continues.
+    Warning,
+    /// Printed to the user, submission is aborted.
+    Error,
+}
+
+/// A single finding reported by a [`ComposeHook`].
+pub type ComposeHookFinding = (Severity, String);
+
+/// Configuration for the compose hook pipeline.
+///
+/// Its fields have the shape of a `[composing]` TOML section, but it is
+/// not read from the TOML config: [`MessageSendCommand`] fills
+/// `disabled_compose_hooks` from `--disable-compose-hook` and leaves the
+/// other fields at their defaults.
+///
+/// [`MessageSendCommand`]: super::command::send::MessageSendCommand
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ComposeHooksConfig {
+    /// Names of built-in hooks to skip, e.g. `["past-date-warn"]`.
+    #[serde(default)]
+    pub disabled_compose_hooks: Vec<String>,
+    /// Overrides the (case-insensitive) keywords `missing-attachment-warn`
+    /// looks for to detect a mention of an attachment. Defaults to
+    /// `["attach", "attached", "attachment", "attachments"]`.
+    ///
+    /// The original request asked for this to be a "language-configurable
+    /// regex". This tree has no `Cargo.toml` to add the `regex` crate
+    /// to, so it is plain case-insensitive substring matching against a
+    /// keyword list instead: no word-boundary or pattern support (e.g.
+    /// a single `attach\w*`-style pattern), and a keyword that is a
+    /// substring of an unrelated word would false-positive. This is a
+    /// deliberate, tracked capability cut pending confirmation that
+    /// keyword matching is an acceptable substitute for regex support,
+    /// not a silent downgrade.
+    #[serde(default)]
+    pub missing_attachment_keywords: Option<Vec<String>>,
+    /// Overrides how far (in seconds) the `Date` header may drift from
+    /// now, in either direction, before `past-date-warn` fires.
+    /// Defaults to two days.
+    #[serde(default)]
+    pub past_date_warn_delta_secs: Option<i64>,
+}
+
+/// A check that runs against a draft before it is submitted.
+pub trait ComposeHook {
+    /// Stable name used in `disabled_compose_hooks` and error messages.
+    fn name(&self) -> &'static str;
+
+    /// Inspects `draft` and returns any findings.
+    fn run(&self, draft: &mail_parser::Message) -> Vec<ComposeHookFinding>;
+}
+
+/// Runs the built-in compose hooks against `draft` in declared order,
+/// skipping any listed in `config.disabled_compose_hooks`.
+///
+/// Returns the collected warnings on success. As soon as a hook
+/// reports an [`Severity::Error`] finding, the pipeline stops and
+/// returns that finding as an `Err`, which should abort submission.
+pub fn run_compose_hooks(
+    draft: &mail_parser::Message,
+    config: &ComposeHooksConfig,
+) -> Result<Vec<ComposeHookFinding>> {
+    let hooks: Vec<Box<dyn ComposeHook>> = vec![
+        Box::new(EmptyDraftWarnHook),
+        Box::new(ImportantHeaderWarnHook),
+        Box::new(MissingAttachmentWarnHook::from_config(config)),
+        Box::new(PastDateWarnHook::from_config(config)),
+    ];
+
+    run_hooks(hooks, draft, config)
+}
+
+/// Runs `hooks` against `draft` in order, as [`run_compose_hooks`] does
+/// for the built-in hooks.
+fn run_hooks(
+    hooks: Vec<Box<dyn ComposeHook>>,
+    draft: &mail_parser::Message,
+    config: &ComposeHooksConfig,
+) -> Result<Vec<ComposeHookFinding>> {
+    let mut warnings = Vec::new();
+
+    for hook in hooks {
+        if config
+            .disabled_compose_hooks
+            .iter()
+            .any(|disabled| disabled == hook.name())
+        {
+            continue;
+        }
+
+        for (severity, message) in hook.run(draft) {
+            match severity {
+                Severity::Error => return Err(eyre!("{}: {message}", hook.name())),
+                Severity::Warning => warnings.push((severity, message)),
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Warns when a draft has neither a subject nor a body.
+struct EmptyDraftWarnHook;
+
+impl ComposeHook for EmptyDraftWarnHook {
+    fn name(&self) -> &'static str {
+        "empty-draft-warn"
+    }
+
+    fn run(&self, draft: &mail_parser::Message) -> Vec<ComposeHookFinding> {
+        let no_subject = draft.subject().unwrap_or("").trim().is_empty();
+        let no_body = draft
+            .body_text(0)
+            .map(|body| body.trim().is_empty())
+            .unwrap_or(true);
+
+        if no_subject && no_body {
+            vec![(
+                Severity::Warning,
+                "draft has no subject and an empty body".to_string(),
+            )]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Warns when `From`, `Date`, or every one of `To`/`Cc`/`Bcc` is missing.
+struct ImportantHeaderWarnHook;
+
+impl ComposeHook for ImportantHeaderWarnHook {
+    fn name(&self) -> &'static str {
+        "important-header-warn"
+    }
+
+    fn run(&self, draft: &mail_parser::Message) -> Vec<ComposeHookFinding> {
+        let mut missing = Vec::new();
+
+        if draft.from().is_none() {
+            missing.push("From");
+        }
+        if draft.date().is_none() {
+            missing.push("Date");
+        }
+        if draft.to().is_none() && draft.cc().is_none() && draft.bcc().is_none() {
+            missing.push("To/Cc/Bcc");
+        }
+
+        if missing.is_empty() {
+            Vec::new()
+        } else {
+            vec![(
+                Severity::Warning,
+                format!("missing or invalid header(s): {}", missing.join(", ")),
+            )]
+        }
+    }
+}
+
+/// Warns when the draft talks about an attachment but carries none.
+struct MissingAttachmentWarnHook {
+    keywords: Vec<String>,
+}
+
+impl MissingAttachmentWarnHook {
+    fn default_keywords() -> Vec<String> {
+        ["attach", "attached", "attachment", "attachments"]
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn from_config(config: &ComposeHooksConfig) -> Self {
+        Self {
+            keywords: config
+                .missing_attachment_keywords
+                .clone()
+                .unwrap_or_else(Self::default_keywords),
+        }
+    }
+
+    fn mentions_attachment(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.keywords
+            .iter()
+            .any(|keyword| text.contains(&keyword.to_lowercase()))
+    }
+}
+
+impl ComposeHook for MissingAttachmentWarnHook {
+    fn name(&self) -> &'static str {
+        "missing-attachment-warn"
+    }
+
+    fn run(&self, draft: &mail_parser::Message) -> Vec<ComposeHookFinding> {
+        if !draft.attachments.is_empty() {
+            return Vec::new();
+        }
+
+        let mentions_attachment = draft.subject().is_some_and(|subject| self.mentions_attachment(subject))
+            || draft
+                .body_text(0)
+                .is_some_and(|body| self.mentions_attachment(&body));
+
+        if mentions_attachment {
+            vec![(
+                Severity::Warning,
+                "message mentions an attachment but none is attached".to_string(),
+            )]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Warns when the `Date` header is too far in the past or future.
+struct PastDateWarnHook {
+    delta_secs: i64,
+}
+
+impl PastDateWarnHook {
+    const DEFAULT_DELTA_SECS: i64 = 60 * 60 * 24 * 2;
+
+    fn from_config(config: &ComposeHooksConfig) -> Self {
+        Self {
+            delta_secs: config
+                .past_date_warn_delta_secs
+                .unwrap_or(Self::DEFAULT_DELTA_SECS),
+        }
+    }
+}
+
+impl ComposeHook for PastDateWarnHook {
+    fn name(&self) -> &'static str {
+        "past-date-warn"
+    }
+
+    fn run(&self, draft: &mail_parser::Message) -> Vec<ComposeHookFinding> {
+        let Some(date) = draft.date() else {
+            return Vec::new();
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default();
+
+        let diff = now - date.to_timestamp();
+
+        if diff.abs() <= self.delta_secs {
+            return Vec::new();
+        }
+
+        let direction = if diff > 0 { "in the past" } else { "in the future" };
+
+        vec![(
+            Severity::Warning,
+            format!(
+                "Date header is more than {}s {direction}",
+                self.delta_secs
+            ),
+        )]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &str) -> mail_parser::Message<'_> {
+        mail_parser::MessageParser::default()
+            .parse(raw.as_bytes())
+            .expect("raw message should parse")
+    }
+
+    #[test]
+    fn empty_draft_warn_fires_only_when_both_subject_and_body_are_empty() {
+        let empty = parse("From: a@b.com\r\nTo: c@d.com\r\nDate: Mon, 1 Jan 2024 00:00:00 +0000\r\n\r\n");
+        assert_eq!(EmptyDraftWarnHook.run(&empty).len(), 1);
+
+        let has_subject = parse("From: a@b.com\r\nSubject: hi\r\n\r\n");
+        assert!(EmptyDraftWarnHook.run(&has_subject).is_empty());
+    }
+
+    #[test]
+    fn important_header_warn_lists_missing_headers() {
+        let draft = parse("Subject: hi\r\n\r\nbody\r\n");
+        let findings = ImportantHeaderWarnHook.run(&draft);
+        assert_eq!(findings.len(), 1);
+        assert!(findings[0].1.contains("From"));
+        assert!(findings[0].1.contains("Date"));
+        assert!(findings[0].1.contains("To/Cc/Bcc"));
+    }
+
+    #[test]
+    fn important_header_warn_passes_when_all_present() {
+        let draft = parse(
+            "From: a@b.com\r\nTo: c@d.com\r\nDate: Mon, 1 Jan 2024 00:00:00 +0000\r\nSubject: hi\r\n\r\nbody\r\n",
+        );
+        assert!(ImportantHeaderWarnHook.run(&draft).is_empty());
+    }
+
+    #[test]
+    fn missing_attachment_warn_fires_on_keyword_without_attachment() {
+        let draft = parse("From: a@b.com\r\nSubject: see attached file\r\n\r\nhi\r\n");
+        let hook = MissingAttachmentWarnHook::from_config(&ComposeHooksConfig::default());
+        assert_eq!(hook.run(&draft).len(), 1);
+    }
+
+    #[test]
+    fn missing_attachment_warn_is_silent_without_keyword() {
+        let draft = parse("From: a@b.com\r\nSubject: hi\r\n\r\nno mention here\r\n");
+        let hook = MissingAttachmentWarnHook::from_config(&ComposeHooksConfig::default());
+        assert!(hook.run(&draft).is_empty());
+    }
+
+    #[test]
+    fn missing_attachment_warn_honours_custom_keywords() {
+        let draft = parse("From: a@b.com\r\nSubject: find the PJ enclosed\r\n\r\nhi\r\n");
+        let config = ComposeHooksConfig {
+            missing_attachment_keywords: Some(vec!["enclosed".to_string()]),
+            ..Default::default()
+        };
+        let hook = MissingAttachmentWarnHook::from_config(&config);
+        assert_eq!(hook.run(&draft).len(), 1);
+    }
+
+    #[test]
+    fn past_date_warn_fires_outside_the_configured_delta() {
+        let draft = parse("From: a@b.com\r\nDate: Mon, 1 Jan 2024 00:00:00 +0000\r\n\r\nhi\r\n");
+        let config = ComposeHooksConfig {
+            past_date_warn_delta_secs: Some(1),
+            ..Default::default()
+        };
+        let hook = PastDateWarnHook::from_config(&config);
+        assert_eq!(hook.run(&draft).len(), 1);
+    }
+
+    #[test]
+    fn past_date_warn_is_silent_within_the_configured_delta() {
+        let draft = parse("From: a@b.com\r\nDate: Mon, 1 Jan 2024 00:00:00 +0000\r\n\r\nhi\r\n");
+        let config = ComposeHooksConfig {
+            past_date_warn_delta_secs: Some(i64::MAX),
+            ..Default::default()
+        };
+        let hook = PastDateWarnHook::from_config(&config);
+        assert!(hook.run(&draft).is_empty());
+    }
+
+    #[test]
+    fn run_compose_hooks_skips_disabled_hooks() {
+        // No subject and an empty body, so `empty-draft-warn` would fire
+        // if it were not disabled.
+        let draft = parse("From: a@b.com\r\nDate: Mon, 1 Jan 2024 00:00:00 +0000\r\n\r\n");
+        let config = ComposeHooksConfig {
+            disabled_compose_hooks: vec!["empty-draft-warn".to_string()],
+            past_date_warn_delta_secs: Some(1),
+            ..Default::default()
+        };
+
+        let warnings = run_compose_hooks(&draft, &config).unwrap();
+        assert!(warnings.iter().all(|(_, message)| !message.contains("empty")));
+        assert!(warnings.iter().any(|(_, message)| message.contains("Date header")));
+    }
+
+    /// A hook that always reports an error, used to exercise the
+    /// abort path of the pipeline (via [`run_hooks`]) without depending
+    /// on a built-in hook ever escalating to `Severity::Error`.
+    struct AlwaysErrorsHook;
+
+    impl ComposeHook for AlwaysErrorsHook {
+        fn name(&self) -> &'static str {
+            "always-errors"
+        }
+
+        fn run(&self, _draft: &mail_parser::Message) -> Vec<ComposeHookFinding> {
+            vec![(Severity::Error, "boom".to_string())]
+        }
+    }
+
+    #[test]
+    fn an_error_finding_aborts_the_pipeline() {
+        let draft = parse("From: a@b.com\r\n\r\nhi\r\n");
+        let findings = AlwaysErrorsHook.run(&draft);
+        assert_eq!(findings[0].0, Severity::Error);
+
+        let hooks: Vec<Box<dyn ComposeHook>> = vec![Box::new(AlwaysErrorsHook)];
+        let err = run_hooks(hooks, &draft, &ComposeHooksConfig::default()).unwrap_err();
+        assert!(err.to_string().contains("always-errors: boom"));
+    }
+}